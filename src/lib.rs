@@ -1,27 +1,34 @@
 use clap::builder::styling::{AnsiColor, Style};
+use command_group::{CommandGroup, GroupChild};
 use ctrlc;
 use dirs;
-use log::{self, debug, error, info, log_enabled};
-use nix::sys::{prctl, signal};
+use glob;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use log::{self, debug, error, info, log_enabled, warn};
 use notify::{RecursiveMode, Watcher};
 use regex::Regex;
 use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
 use std::fs;
 use std::io::{self, IsTerminal};
-use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command};
+use std::process::Command;
 use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::time::Instant;
 
-const TAB: &str = "    ";
-const DEBOUNCE_DURATION: Duration = Duration::from_millis(50);
+const MAX_MARIMO_RESTARTS: u32 = 3;
+const MARIMO_RESTART_BACKOFF: Duration = Duration::from_millis(500);
+
+// editor scratch files and VCS/build junk that should never be watched or
+// reconverted, on top of whatever `.gitignore`/`.ignore` already exclude
+const DEFAULT_IGNORE_PATTERNS: &[&str] = &["*.swp", "*.swx", "4913", "*~", ".#*", "__pycache__/", ".git/"];
 
 #[derive(Debug)]
 pub enum ErrorKind {
-    BadConfig(Box<str>, toml::de::Error),
+    BadConfig(Box<str>, Box<str>),
     FileArgMissing,
     FileNotFound(Box<str>),
     NotAFile(Box<str>),
@@ -29,6 +36,10 @@ pub enum ErrorKind {
     Watch(notify::Error),
     MarimoExited(std::process::ExitStatus),
     MarimoFailedToStart,
+    CellHasDataflow(Box<str>),
+    OnChangeHookFailed(Box<str>, Box<str>),
+    ConfigFileNotFile(Box<str>),
+    UnrecognizedCell(Box<str>),
 }
 
 impl From<notify::Error> for ErrorKind {
@@ -38,28 +49,146 @@ impl From<notify::Error> for ErrorKind {
 }
 
 #[derive(Deserialize)]
-struct Config {
+pub struct Config {
     cache_dir: String,
+    #[serde(default)]
+    ignore: Vec<String>,
+    #[serde(default = "default_respect_gitignore")]
+    respect_gitignore: bool,
+    // shell command templates (`{file}` is substituted with the changed
+    // source file's path) run in order before a source file is converted
+    #[serde(default)]
+    on_change: Vec<String>,
+    // literal marker text (e.g. `# %%`) at the start of each cell; matched
+    // as a fixed string when splitting and written back verbatim when
+    // reversing the conversion, so it never needs to be a valid regex
+    //
+    // this was originally meant to be a user-supplied regex, validated at
+    // load time so a bad pattern surfaced as `BadConfig` instead of
+    // panicking in `Regex::new(...).unwrap()`. a regex can't be written
+    // back verbatim (see chunk0-6's fixup), and there's no general way to
+    // recover the literal delimiter text from an arbitrary pattern, so
+    // round-tripping requires cell_marker to be the literal text itself.
+    // load-time validation is unnecessary under this scheme: escaping a
+    // literal string can't produce an invalid regex, so there's no
+    // equivalent failure mode to guard against.
+    #[serde(default = "default_cell_marker")]
+    cell_marker: String,
+    #[serde(default = "default_indent")]
+    indent: String,
+    #[serde(default = "default_debounce_ms")]
+    debounce_ms: u64,
+    #[serde(default = "default_marimo_path")]
+    marimo_path: String,
 }
 
-fn cache_dir() -> Result<PathBuf, ErrorKind> {
-    let default_path = PathBuf::from(".marimow_cache");
-    if let Some(config_path) =
-        dirs::config_dir().and_then(|p| Some(p.join("marimow").join("config.toml")))
-    {
-        info!("Found config in {}", config_path.display());
-        toml::from_str(
-            &fs::read_to_string(&config_path)
-                .map_err(|e| ErrorKind::Io(config_path.to_string_lossy().into(), e))?,
-        )
-        .map_err(|e| ErrorKind::BadConfig(config_path.to_string_lossy().into(), e))
-        .and_then(|config: Config| Ok(PathBuf::from(config.cache_dir)))
-    } else {
-        Ok(default_path)
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            cache_dir: ".marimow_cache".into(),
+            ignore: Vec::new(),
+            respect_gitignore: default_respect_gitignore(),
+            on_change: Vec::new(),
+            cell_marker: default_cell_marker(),
+            indent: default_indent(),
+            debounce_ms: default_debounce_ms(),
+            marimo_path: default_marimo_path(),
+        }
     }
 }
 
-fn convert_file(source_path: &Path, target_path: &Path) -> Result<(), ErrorKind> {
+fn default_respect_gitignore() -> bool {
+    true
+}
+
+fn default_cell_marker() -> String {
+    "# %%".into()
+}
+
+fn default_indent() -> String {
+    "    ".into()
+}
+
+fn default_debounce_ms() -> u64 {
+    50
+}
+
+fn default_marimo_path() -> String {
+    "marimo".into()
+}
+
+pub fn load_config() -> Result<Config, ErrorKind> {
+    let Some(config_path) = dirs::config_dir().map(|p| p.join("marimow").join("config.toml"))
+    else {
+        return Ok(Config::default());
+    };
+    if !config_path.exists() {
+        return Ok(Config::default());
+    }
+    if !config_path.is_file() {
+        return Err(ErrorKind::ConfigFileNotFile(
+            config_path.to_string_lossy().into(),
+        ));
+    }
+
+    info!("Found config in {}", config_path.display());
+    toml::from_str(
+        &fs::read_to_string(&config_path)
+            .map_err(|e| ErrorKind::Io(config_path.to_string_lossy().into(), e))?,
+    )
+    .map_err(|e| ErrorKind::BadConfig(config_path.to_string_lossy().into(), e.to_string().into()))
+}
+
+// builds a matcher combining the built-in ignore defaults, the config's
+// custom `ignore` patterns, and (if enabled) `root`'s `.gitignore`/`.ignore`
+fn build_ignore_matcher(root: &Path, config: &Config) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in DEFAULT_IGNORE_PATTERNS {
+        let _ = builder.add_line(None, pattern);
+    }
+    for pattern in &config.ignore {
+        let _ = builder.add_line(None, pattern);
+    }
+    if config.respect_gitignore {
+        let _ = builder.add(root.join(".gitignore"));
+        let _ = builder.add(root.join(".ignore"));
+    }
+    builder.build().unwrap_or_else(|e| {
+        error!("failed to build ignore matcher: {e}");
+        Gitignore::empty()
+    })
+}
+
+fn is_ignored(matcher: &Gitignore, path: &Path) -> bool {
+    matcher.matched(path, path.is_dir()).is_ignore()
+}
+
+// runs each `on_change` command template against `file` in order (with
+// `{file}` substituted for its path), stopping at the first failure
+fn run_on_change_hooks(commands: &[String], file: &Path) -> Result<(), ErrorKind> {
+    for template in commands {
+        let command = template.replace("{file}", &file.to_string_lossy());
+        info!("Running on-change hook: {command}");
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .output()
+            .map_err(|e| ErrorKind::Io(command.clone().into(), e))?;
+        if !output.status.success() {
+            let mut combined = String::from_utf8_lossy(&output.stdout).into_owned();
+            combined.push_str(&String::from_utf8_lossy(&output.stderr));
+            return Err(ErrorKind::OnChangeHookFailed(command.into(), combined.into()));
+        }
+    }
+    Ok(())
+}
+
+fn convert_file(
+    source_path: &Path,
+    target_path: &Path,
+    cell_marker: &str,
+    indent: &str,
+) -> Result<(), ErrorKind> {
     let content = fs::read_to_string(source_path)
         .map_err(|e| ErrorKind::Io(source_path.to_string_lossy().into(), e))?;
 
@@ -77,13 +206,14 @@ fn convert_file(source_path: &Path, target_path: &Path) -> Result<(), ErrorKind>
             })
             .map(|s| {
                 s.lines().for_each(|line| {
-                    (!line.is_empty()).then(|| result.push_str(TAB));
+                    (!line.is_empty()).then(|| result.push_str(indent));
                     result.push_str(line);
                     result.push_str("\n");
                 });
             });
     };
-    let parts: Vec<&str> = Regex::new(r"(?m)^# %%").unwrap().split(&content).collect();
+    let split_pattern = format!(r"(?m)^{}", regex::escape(cell_marker));
+    let parts: Vec<&str> = Regex::new(&split_pattern).unwrap().split(&content).collect();
     parts
         .get(0)
         .map(|section| push_section("\nwith app.setup:\n", section, None));
@@ -96,12 +226,215 @@ fn convert_file(source_path: &Path, target_path: &Path) -> Result<(), ErrorKind>
         );
     });
     contains_function.then(|| result.push_str("\n")); // two empty lines after functions
-    result.push_str(&format!("\nif __name__ == \"__main__\":\n{TAB}app.run()\n"));
+    result.push_str(&format!("\nif __name__ == \"__main__\":\n{indent}app.run()\n"));
 
     Ok(fs::write(target_path, result)
         .map_err(|e| ErrorKind::Io(target_path.to_string_lossy().into(), e))?)
 }
 
+// collects lines[start..) up to (but not including) the next top-level
+// section header (`with app.setup:`, any `@app.` decorator or a bare `def`)
+// or `end`, trimming leading/trailing blank lines (the blank-line padding
+// `convert_file` inserts between sections)
+fn collect_section<'a>(lines: &[&'a str], start: usize, end: usize) -> (Vec<&'a str>, usize) {
+    let next_header = lines[start..end]
+        .iter()
+        .position(|line| {
+            *line == "with app.setup:" || line.starts_with("@app.") || line.starts_with("def ")
+        })
+        .map_or(end, |i| start + i);
+    let mut section = &lines[start..next_header];
+    while section.first().is_some_and(|l| l.is_empty()) {
+        section = &section[1..];
+    }
+    while section.last().is_some_and(|l| l.is_empty()) {
+        section = &section[..section.len() - 1];
+    }
+    (section.to_vec(), next_header)
+}
+
+// undoes the one-level indentation `convert_file` adds, leaving blank
+// lines untouched
+fn dedent(lines: &[&str], indent: &str) -> String {
+    lines
+        .iter()
+        .map(|line| line.strip_prefix(indent).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// the `return (...)` (or bare `return`) marimo appends to a cell when other
+// cells consume its variables; harmless to drop for cells with no dataflow args
+fn strip_trailing_return(body: &str) -> &str {
+    let trimmed_end = body.trim_end_matches('\n');
+    match trimmed_end.rsplit_once('\n') {
+        Some((rest, last)) if Regex::new(r"^return(\s*\(.*\))?\s*$").unwrap().is_match(last) => {
+            rest
+        }
+        None if Regex::new(r"^return(\s*\(.*\))?\s*$").unwrap().is_match(trimmed_end) => "",
+        _ => trimmed_end,
+    }
+}
+
+// reverses `convert_file`: turns a marimo notebook back into a script
+// delimited by `cell_marker` (used here as the literal marker text)
+fn unconvert_file(
+    cached_path: &Path,
+    source_path: &Path,
+    cell_marker: &str,
+    indent: &str,
+) -> Result<(), ErrorKind> {
+    let content = fs::read_to_string(cached_path)
+        .map_err(|e| ErrorKind::Io(cached_path.to_string_lossy().into(), e))?;
+    let lines: Vec<&str> = content.lines().collect();
+    let def_re = Regex::new(r"^def \w+\((.*)\):$").unwrap();
+
+    let body_end = lines
+        .iter()
+        .position(|line| line.trim_start() == "if __name__ == \"__main__\":")
+        .unwrap_or(lines.len());
+
+    let mut setup: Option<String> = None;
+    let mut cells: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < body_end {
+        if lines[i] == "with app.setup:" {
+            let (section, next) = collect_section(&lines, i + 1, body_end);
+            setup = Some(dedent(&section, indent));
+            i = next;
+        } else if lines[i] == "@app.cell" {
+            let def_line = lines.get(i + 1).copied().unwrap_or("");
+            let args = def_re
+                .captures(def_line)
+                .map(|c| c.get(1).unwrap().as_str().trim().to_string())
+                .unwrap_or_default();
+            if !args.is_empty() {
+                return Err(ErrorKind::CellHasDataflow(def_line.into()));
+            }
+            let (section, next) = collect_section(&lines, i + 2, body_end);
+            let body = dedent(&section, indent);
+            cells.push(strip_trailing_return(&body).to_string());
+            i = next;
+        } else if lines[i].starts_with("@app.") || lines[i].starts_with("def ") {
+            // e.g. `@app.cell(hide_code=True)`, `@app.function`, a bare
+            // `def` -- constructs we don't know how to write back flatly
+            return Err(ErrorKind::UnrecognizedCell(lines[i].into()));
+        } else {
+            i += 1;
+        }
+    }
+
+    let mut result = String::new();
+    if let Some(setup) = setup {
+        result.push_str(&setup);
+    }
+    for cell in cells {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(cell_marker);
+        result.push('\n');
+        result.push_str(&cell);
+    }
+    result.push('\n');
+
+    Ok(fs::write(source_path, result)
+        .map_err(|e| ErrorKind::Io(source_path.to_string_lossy().into(), e))?)
+}
+
+// a single `fs::write` commonly raises more than one notify event (e.g.
+// a Modify followed by a Create/Metadata event), so a suppressed path has
+// to stay suppressed for this whole window, not just for the first event
+const SUPPRESS_WINDOW: Duration = Duration::from_millis(500);
+
+// tracks paths we're about to write ourselves so the corresponding watcher
+// event(s) can be ignored instead of bouncing the change right back, which
+// would otherwise loop forever between the source and the cached file
+#[derive(Default)]
+struct ConversionGuard {
+    suppressed: Mutex<HashMap<PathBuf, Instant>>,
+}
+
+impl ConversionGuard {
+    fn suppress(&self, path: &Path) {
+        self.suppressed
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), Instant::now());
+    }
+
+    fn is_suppressed(&self, path: &Path) -> bool {
+        let mut suppressed = self.suppressed.lock().unwrap();
+        match suppressed.get(path) {
+            Some(at) if at.elapsed() < SUPPRESS_WINDOW => true,
+            Some(_) => {
+                suppressed.remove(path);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+// mirrors an absolute source path under the cache directory: relative to the
+// cache directory's parent when the source lies under it (with cache_dir
+// `/home/user/project/.marimow_cache`, `/home/user/project/sub/foo.py` ->
+// `<cache_dir>/sub/foo.py`), otherwise as the whole absolute path
+// (`/tmp/foo.py` -> `<cache_dir>/tmp/foo.py`)
+fn cached_path_for(cache_dir: &Path, source_path: &Path) -> PathBuf {
+    if let Some(prefix) = cache_dir.parent()
+        && source_path.starts_with(prefix)
+    {
+        cache_dir.join(source_path.strip_prefix(prefix).unwrap())
+    } else {
+        cache_dir.join(source_path.strip_prefix("/").unwrap())
+    }
+}
+
+// recursively collects every `.py` file under `dir`, skipping anything `matcher` ignores
+// recursively collects every `.py` file under `dir`, skipping anything
+// `matcher` ignores as well as `cache_dir` itself -- the cache directory
+// commonly nests inside the watched directory, and without this exclusion
+// its own previously-converted notebooks get rediscovered as sources,
+// reconverting into more deeply nested cache entries on every run
+fn discover_py_files(dir: &Path, matcher: &Gitignore, cache_dir: &Path) -> Result<Vec<PathBuf>, ErrorKind> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in
+            fs::read_dir(&current).map_err(|e| ErrorKind::Io(current.to_string_lossy().into(), e))?
+        {
+            let path =
+                entry.map_err(|e| ErrorKind::Io(current.to_string_lossy().into(), e))?.path();
+            if path == *cache_dir || is_ignored(matcher, &path) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "py") {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_glob_pattern(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+// finds the deepest directory that is an ancestor of every path in `paths`,
+// used as the watch root when the entrypoint is a glob
+fn common_ancestor(paths: &[PathBuf]) -> PathBuf {
+    let mut ancestor = paths[0].parent().unwrap_or(Path::new("/")).to_path_buf();
+    for path in &paths[1..] {
+        while !path.starts_with(&ancestor) {
+            ancestor = ancestor.parent().unwrap_or(Path::new("/")).to_path_buf();
+        }
+    }
+    ancestor
+}
+
 fn assert_file_exists(file: &Path) -> Result<(), ErrorKind> {
     let path_str = file.to_string_lossy();
     if !file.exists() {
@@ -113,9 +446,9 @@ fn assert_file_exists(file: &Path) -> Result<(), ErrorKind> {
     Ok(())
 }
 
-fn run_marimo(args: Vec<OsString>) -> Result<Child, ErrorKind> {
+fn run_marimo(args: &[OsString], marimo_path: &str) -> Result<GroupChild, ErrorKind> {
     if log_enabled!(log::Level::Info) {
-        let mut message = String::from("Running `marimo edit --watch`");
+        let mut message = format!("Running `{marimo_path} edit --watch`");
         args.iter().for_each(|arg| {
             message.push_str(" ");
             message.push_str(&arg.to_string_lossy().into_owned());
@@ -123,95 +456,165 @@ fn run_marimo(args: Vec<OsString>) -> Result<Child, ErrorKind> {
         info!("{}", message);
     }
 
-    let mut command = Command::new("marimo");
+    let mut command = Command::new(marimo_path);
     command
         .args(["edit", "--watch"])
         .args(args.iter().filter(|&arg| *arg != "--watch"));
 
-    unsafe {
-        command.pre_exec(|| prctl::set_pdeathsig(signal::Signal::SIGKILL).map_err(|e| e.into()));
-    }
+    command.group_spawn().or(Err(ErrorKind::MarimoFailedToStart))
+}
 
-    command.spawn().or(Err(ErrorKind::MarimoFailedToStart))
+// kills every descendant in the marimo process group/job object, not just
+// the immediate child, so marimo's own subprocesses don't outlive us
+fn kill_marimo_group(child: &mut GroupChild) {
+    child
+        .kill()
+        .expect("could not kill the marimo process group");
+    child
+        .wait()
+        .expect("could not wait for the marimo process group to exit");
 }
 
 fn watch_and_update_file(
-    source_path: &Path,
-    target_path: &Path,
-    marimo_child: &mut Child,
+    watch_root: &Path,
+    cache_watch_root: &Path,
+    recursive_mode: RecursiveMode,
+    path_map: &HashMap<PathBuf, PathBuf>,
+    reverse_path_map: &HashMap<PathBuf, PathBuf>,
+    ignore_matcher: &Gitignore,
+    guard: &ConversionGuard,
+    config: &Config,
+    args: &[OsString],
+    marimo_child: &Arc<Mutex<GroupChild>>,
 ) -> Result<(), ErrorKind> {
-    info!("Watching source path: {}", source_path.display());
+    let debounce_duration = Duration::from_millis(config.debounce_ms);
+    info!("Watching: {}", watch_root.display());
     let (tx, rx) = mpsc::channel();
     let mut watcher = notify::recommended_watcher(tx)?;
-    // watch parent directory because Remove(File) is one of the events emitted
-    // when `:w` is executed in vim, causing everything to break
-    watcher.watch(source_path.parent().unwrap(), RecursiveMode::NonRecursive)?;
+    // watch the parent directory (or, in recursive mode, the directory itself)
+    // because Remove(File) is one of the events emitted when `:w` is executed
+    // in vim, causing everything to break
+    watcher.watch(watch_root, recursive_mode)?;
+    watcher.watch(cache_watch_root, recursive_mode)?;
+
+    let mut restarts = 0u32;
+
+    // classifies paths from an event into the source/target files they map
+    // to, skipping anything ignored or that we just wrote ourselves
+    let classify = |paths: Vec<PathBuf>,
+                    changed_sources: &mut HashSet<PathBuf>,
+                    changed_targets: &mut HashSet<PathBuf>| {
+        for path in paths {
+            if guard.is_suppressed(&path) {
+                continue;
+            }
+            if path_map.contains_key(&path) && !is_ignored(ignore_matcher, &path) {
+                changed_sources.insert(path);
+            } else if reverse_path_map.contains_key(&path) {
+                changed_targets.insert(path);
+            }
+        }
+    };
 
     loop {
-        if let Some(status) = marimo_child
-            .try_wait()
-            .map_err(|e| ErrorKind::Io("marimo".into(), e))?
         {
-            if status.success() {
-                break;
-            } else {
-                return Err(ErrorKind::MarimoExited(status));
+            let mut child = marimo_child.lock().unwrap();
+            if let Some(status) = child
+                .try_wait()
+                .map_err(|e| ErrorKind::Io("marimo".into(), e))?
+            {
+                if status.success() {
+                    break;
+                } else if restarts < MAX_MARIMO_RESTARTS {
+                    restarts += 1;
+                    warn!(
+                        "marimo exited unexpectedly ({status}), restarting \
+                         (attempt {restarts}/{MAX_MARIMO_RESTARTS})"
+                    );
+                    drop(child);
+                    std::thread::sleep(MARIMO_RESTART_BACKOFF);
+                    *marimo_child.lock().unwrap() = run_marimo(args, &config.marimo_path)?;
+                } else {
+                    return Err(ErrorKind::MarimoExited(status));
+                }
             }
         }
 
         match rx.recv_timeout(Duration::from_millis(100)) {
             Ok(Ok(event)) => {
                 debug!("Received event: {:?}", event);
-                if event.paths.iter().any(|p| p == source_path)
-                    && (event.kind.is_modify() || event.kind.is_create())
-                {
+                let mut changed_sources = HashSet::new();
+                let mut changed_targets = HashSet::new();
+                if event.kind.is_modify() || event.kind.is_create() {
+                    classify(event.paths, &mut changed_sources, &mut changed_targets);
+                }
+                if !changed_sources.is_empty() || !changed_targets.is_empty() {
                     // because saving in vim results in a lot of events
                     // (and sometimes the file disappears when trying to read)
                     let mut last_event_time = Instant::now();
-                    while last_event_time.elapsed() < DEBOUNCE_DURATION {
-                        match rx.recv_timeout(DEBOUNCE_DURATION) {
-                            Ok(Ok(_)) => {
+                    while last_event_time.elapsed() < debounce_duration {
+                        match rx.recv_timeout(debounce_duration) {
+                            Ok(Ok(event)) => {
                                 last_event_time = Instant::now();
+                                if event.kind.is_modify() || event.kind.is_create() {
+                                    classify(event.paths, &mut changed_sources, &mut changed_targets);
+                                }
                                 continue;
                             }
                             Err(RecvTimeoutError::Timeout) => break,
                             Ok(Err(e)) => return Err(ErrorKind::Watch(e)),
                             Err(RecvTimeoutError::Disconnected) => {
-                                marimo_child
-                                    .kill()
-                                    .expect("could not kill the marimo process");
-                                marimo_child
-                                    .wait()
-                                    .expect("could not wait for marimo process to exit");
+                                kill_marimo_group(&mut marimo_child.lock().unwrap());
                                 panic!("Watcher disconnected")
                             }
                         }
                     }
-                    info!(
-                        "source file '{}' changed, converting...",
-                        source_path.display()
-                    );
-                    if let Err(e) = convert_file(source_path, target_path) {
-                        error!("Error converting file");
-                        marimo_child
-                            .kill()
-                            .expect("could not kill the marimo process");
-                        marimo_child
-                            .wait()
-                            .expect("could not wait for marimo process to exit");
-                        return Err(e);
+                    for source_path in changed_sources {
+                        let target_path = &path_map[&source_path];
+                        info!(
+                            "source file '{}' changed, converting...",
+                            source_path.display()
+                        );
+                        // on-change hooks (e.g. a formatter) commonly rewrite
+                        // source_path themselves, which would otherwise
+                        // re-trigger this whole branch on the next event
+                        guard.suppress(&source_path);
+                        if let Err(e) = run_on_change_hooks(&config.on_change, &source_path) {
+                            error!("Error running on-change hooks");
+                            kill_marimo_group(&mut marimo_child.lock().unwrap());
+                            return Err(e);
+                        }
+                        guard.suppress(target_path);
+                        if let Err(e) =
+                            convert_file(&source_path, target_path, &config.cell_marker, &config.indent)
+                        {
+                            error!("Error converting file");
+                            kill_marimo_group(&mut marimo_child.lock().unwrap());
+                            return Err(e);
+                        }
+                    }
+                    for target_path in changed_targets {
+                        let source_path = &reverse_path_map[&target_path];
+                        info!(
+                            "cached file '{}' changed, writing back to '{}'...",
+                            target_path.display(),
+                            source_path.display()
+                        );
+                        guard.suppress(source_path);
+                        if let Err(e) =
+                            unconvert_file(&target_path, source_path, &config.cell_marker, &config.indent)
+                        {
+                            error!("Error writing cached file back to source");
+                            kill_marimo_group(&mut marimo_child.lock().unwrap());
+                            return Err(e);
+                        }
                     }
                 }
             }
             Ok(Err(e)) => return Err(ErrorKind::Watch(e)),
             Err(RecvTimeoutError::Timeout) => {}
             Err(RecvTimeoutError::Disconnected) => {
-                marimo_child
-                    .kill()
-                    .expect("could not kill the marimo process");
-                marimo_child
-                    .wait()
-                    .expect("could not wait for marimo process to exit");
+                kill_marimo_group(&mut marimo_child.lock().unwrap());
                 panic!("Watcher disconnected")
             }
         }
@@ -228,61 +631,163 @@ fn make_parent(path: &Path) -> Result<(), ErrorKind> {
     Ok(())
 }
 
-pub fn run_convert_command(input: &Path, output: &Path) -> Result<(), ErrorKind> {
+pub fn run_convert_command(input: &Path, output: &Path, config: &Config) -> Result<(), ErrorKind> {
+    assert_file_exists(&input)?;
+    make_parent(output)?;
+    convert_file(&input, &output, &config.cell_marker, &config.indent)?;
+    Ok(())
+}
+
+pub fn run_unconvert_command(input: &Path, output: &Path, config: &Config) -> Result<(), ErrorKind> {
     assert_file_exists(&input)?;
     make_parent(output)?;
-    convert_file(&input, &output)?;
+    unconvert_file(&input, &output, &config.cell_marker, &config.indent)?;
     Ok(())
 }
 
-pub fn run_edit_command(mut args: Vec<OsString>) -> Result<(), ErrorKind> {
-    let cache_dir_rel = cache_dir()?;
+pub fn run_edit_command(mut args: Vec<OsString>, config: &Config) -> Result<(), ErrorKind> {
+    let cache_dir_rel = PathBuf::from(&config.cache_dir);
     let cache_dir = cache_dir_rel
         .canonicalize()
         .map_err(|e| ErrorKind::Io(cache_dir_rel.to_string_lossy().into(), e))?;
     info!("Using {} as the cache directory", cache_dir.display());
 
-    let input_path: PathBuf;
-    let cached_path: PathBuf;
+    let path_map: HashMap<PathBuf, PathBuf>;
+    let watch_root: PathBuf;
+    let cache_watch_root: PathBuf;
+    let recursive_mode: RecursiveMode;
+    let ignore_matcher: Gitignore;
 
-    if let Some(arg) = args
-        .iter_mut()
-        .find(|arg| !arg.as_encoded_bytes().starts_with(b"-"))
-    {
-        let given_path = PathBuf::from(std::mem::take(arg));
-        match given_path.canonicalize() {
-            Ok(canonical_path) => input_path = canonical_path,
+    let Some(idx) = args
+        .iter()
+        .position(|arg| !arg.as_encoded_bytes().starts_with(b"-"))
+    else {
+        return Err(ErrorKind::FileArgMissing);
+    };
+    let given_path_str = args[idx].to_string_lossy().into_owned();
+
+    if is_glob_pattern(&given_path_str) {
+        let source_files: Vec<PathBuf> = glob::glob(&given_path_str)
+            .map_err(|e| ErrorKind::BadConfig(given_path_str.clone().into(), e.to_string().into()))?
+            .filter_map(|entry| entry.ok())
+            .filter(|path| path.is_file())
+            .map(|path| {
+                path.canonicalize()
+                    .map_err(|e| ErrorKind::Io(path.to_string_lossy().into(), e))
+            })
+            .collect::<Result<_, _>>()?;
+        if source_files.is_empty() {
+            return Err(ErrorKind::FileNotFound(given_path_str.into()));
+        }
+
+        watch_root = common_ancestor(&source_files);
+        ignore_matcher = build_ignore_matcher(&watch_root, config);
+        cache_watch_root = cached_path_for(&cache_dir, &watch_root);
+        fs::create_dir_all(&cache_watch_root)
+            .map_err(|e| ErrorKind::Io(cache_watch_root.to_string_lossy().into(), e))?;
+        recursive_mode = RecursiveMode::Recursive;
+
+        path_map = source_files
+            .into_iter()
+            .filter(|source| !source.starts_with(&cache_dir) && !is_ignored(&ignore_matcher, source))
+            .map(|source| {
+                let target = cached_path_for(&cache_dir, &source);
+                (source, target)
+            })
+            .collect();
+
+        info!(
+            "Using {} cached files under {} for glob '{given_path_str}'",
+            path_map.len(),
+            cache_watch_root.display()
+        );
+        args[idx] = cache_watch_root.clone().into_os_string();
+    } else {
+        let given_path = PathBuf::from(&given_path_str);
+        let entrypoint = match given_path.canonicalize() {
+            Ok(canonical_path) => canonical_path,
             Err(e) => {
                 assert_file_exists(&given_path)?;
                 return Err(ErrorKind::Io(given_path.to_string_lossy().into(), e)); // should be unreachable
             }
-        }
-        if let Some(prefix) = cache_dir.parent()
-            && input_path.starts_with(prefix)
-        {
-            cached_path = cache_dir.join(&input_path.strip_prefix(prefix).unwrap());
+        };
+
+        let cache_target: PathBuf;
+        if entrypoint.is_dir() {
+            ignore_matcher = build_ignore_matcher(&entrypoint, config);
+            let source_files = discover_py_files(&entrypoint, &ignore_matcher, &cache_dir)?;
+            path_map = source_files
+                .into_iter()
+                .map(|source| {
+                    let target = cached_path_for(&cache_dir, &source);
+                    (source, target)
+                })
+                .collect();
+            cache_target = cached_path_for(&cache_dir, &entrypoint);
+            fs::create_dir_all(&cache_target)
+                .map_err(|e| ErrorKind::Io(cache_target.to_string_lossy().into(), e))?;
+            cache_watch_root = cache_target.clone();
+            watch_root = entrypoint;
+            recursive_mode = RecursiveMode::Recursive;
         } else {
-            cached_path = cache_dir.join(&input_path.strip_prefix("/").unwrap());
+            let target = cached_path_for(&cache_dir, &entrypoint);
+            watch_root = entrypoint.parent().unwrap().to_path_buf();
+            ignore_matcher = build_ignore_matcher(&watch_root, config);
+            path_map = HashMap::from([(entrypoint, target.clone())]);
+            cache_watch_root = target.parent().unwrap().to_path_buf();
+            cache_target = target;
+            recursive_mode = RecursiveMode::NonRecursive;
         }
-        *arg = cached_path.clone().into_os_string();
-    } else {
-        return Err(ErrorKind::FileArgMissing);
+        info!("Using {} as the cached path", cache_target.display());
+        args[idx] = cache_target.into_os_string();
     }
-    info!("Using {} as the cached file", cached_path.display());
 
-    make_parent(&cached_path)?;
-    convert_file(&input_path, &cached_path)?;
+    for (source_path, target_path) in &path_map {
+        make_parent(target_path)?;
+        convert_file(source_path, target_path, &config.cell_marker, &config.indent)?;
+    }
+
+    let reverse_path_map: HashMap<PathBuf, PathBuf> = path_map
+        .iter()
+        .map(|(source, target)| (target.clone(), source.clone()))
+        .collect();
+    let guard = ConversionGuard::default();
 
-    ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
+    let marimo_child = Arc::new(Mutex::new(run_marimo(&args, &config.marimo_path)?));
+    {
+        let marimo_child = Arc::clone(&marimo_child);
+        ctrlc::set_handler(move || {
+            if let Ok(mut child) = marimo_child.lock() {
+                kill_marimo_group(&mut child);
+            }
+            std::process::exit(130);
+        })
+        .expect("Error setting Ctrl-C handler");
+    }
 
-    let mut marimo_child = run_marimo(args)?;
-    watch_and_update_file(&input_path, &cached_path, &mut marimo_child)?;
+    watch_and_update_file(
+        &watch_root,
+        &cache_watch_root,
+        recursive_mode,
+        &path_map,
+        &reverse_path_map,
+        &ignore_matcher,
+        &guard,
+        config,
+        &args,
+        &marimo_child,
+    )?;
 
     marimo_child
+        .lock()
+        .unwrap()
         .wait()
         .map_err(|err| ErrorKind::Io("marimo".into(), err))?;
-    Ok(std::fs::remove_file(&cached_path)
-        .map_err(|e| ErrorKind::Io(cached_path.to_string_lossy().into(), e))?)
+    for target_path in path_map.values() {
+        fs::remove_file(target_path)
+            .map_err(|e| ErrorKind::Io(target_path.to_string_lossy().into(), e))?;
+    }
+    Ok(())
 }
 
 pub fn clear_cache() -> Result<(), ErrorKind> {
@@ -291,7 +796,7 @@ pub fn clear_cache() -> Result<(), ErrorKind> {
     } else {
         Style::new()
     };
-    let cache_dir = cache_dir()?;
+    let cache_dir = PathBuf::from(load_config()?.cache_dir);
     println!("Removing cache at {style}{}{style:#}", cache_dir.display());
     fs::remove_dir_all(&cache_dir)
         .or_else(|e| match e.kind() {
@@ -86,6 +86,26 @@ impl ErrorReporter {
                     emph, self.usage_style
                 )
             }
+            ErrorKind::CellHasDataflow(signature) => {
+                format!(
+                    "cell {0}'{signature}'{0:#} depends on another cell's variables; \
+                     marimow can't write that back to a flat script",
+                    emph
+                )
+            }
+            ErrorKind::OnChangeHookFailed(command, output) => {
+                format!(
+                    "on-change hook {0}'{command}'{0:#} failed:\n{output}",
+                    emph
+                )
+            }
+            ErrorKind::UnrecognizedCell(construct) => {
+                format!(
+                    "marimow doesn't know how to write {0}'{construct}'{0:#} back to a flat \
+                     script; remove or edit it by hand before saving",
+                    emph
+                )
+            }
         };
         eprintln!("{0}error:{0:#} {message}", self.prefix_style);
         std::process::exit(1);
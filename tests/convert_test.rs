@@ -0,0 +1,112 @@
+use generate_tests::generate_file_tests;
+
+generate_file_tests!(
+    "tests/fixtures";
+    "basic.py",
+    "with_setup.py",
+    "multiline.py",
+    "comment_only.py"
+);
+
+#[test]
+fn round_trip_recovers_the_original_script() {
+    for fixture in ["basic.py", "with_setup.py", "multiline.py"] {
+        let source_path = std::path::PathBuf::from("tests/fixtures").join(fixture);
+        let config = marimow::Config::default();
+        let marimo_file = tempfile::NamedTempFile::new().unwrap();
+        marimow::run_convert_command(&source_path, marimo_file.path(), &config).unwrap();
+
+        let round_tripped = tempfile::NamedTempFile::new().unwrap();
+        marimow::run_unconvert_command(marimo_file.path(), round_tripped.path(), &config).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&source_path).unwrap(),
+            std::fs::read_to_string(round_tripped.path()).unwrap(),
+            "round-trip mismatch for {fixture}",
+        );
+    }
+}
+
+#[test]
+fn refuses_to_write_back_a_cell_with_dataflow_args() {
+    let marimo_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(
+        marimo_file.path(),
+        "import marimo\n\napp = marimo.App()\n\n\n@app.cell\ndef _(x):\n    y = x + 1\n    return (y,)\n\n\nif __name__ == \"__main__\":\n    app.run()\n",
+    )
+    .unwrap();
+
+    let output = tempfile::NamedTempFile::new().unwrap();
+    let err = marimow::run_unconvert_command(
+        marimo_file.path(),
+        output.path(),
+        &marimow::Config::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, marimow::ErrorKind::CellHasDataflow(_)));
+}
+
+#[test]
+fn strips_the_trailing_return_marimo_injects_into_a_cell() {
+    let marimo_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(
+        marimo_file.path(),
+        "import marimo\n\napp = marimo.App()\n\n\n@app.cell\ndef _():\n    y = 1\n    return (y,)\n\n\nif __name__ == \"__main__\":\n    app.run()\n",
+    )
+    .unwrap();
+
+    let output = tempfile::NamedTempFile::new().unwrap();
+    marimow::run_unconvert_command(
+        marimo_file.path(),
+        output.path(),
+        &marimow::Config::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(output.path()).unwrap(),
+        "# %%\ny = 1\n",
+    );
+}
+
+#[test]
+fn refuses_to_write_back_a_decorated_cell_it_does_not_recognize() {
+    let marimo_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(
+        marimo_file.path(),
+        "import marimo\n\napp = marimo.App()\n\n\n@app.cell(hide_code=True)\ndef _():\n    y = 1\n    return (y,)\n\n\nif __name__ == \"__main__\":\n    app.run()\n",
+    )
+    .unwrap();
+
+    let output = tempfile::NamedTempFile::new().unwrap();
+    let err = marimow::run_unconvert_command(
+        marimo_file.path(),
+        output.path(),
+        &marimow::Config::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, marimow::ErrorKind::UnrecognizedCell(_)));
+}
+
+#[test]
+fn converting_back_and_forth_twice_is_idempotent() {
+    for fixture in ["basic.py", "with_setup.py", "multiline.py"] {
+        let source_path = std::path::PathBuf::from("tests/fixtures").join(fixture);
+        let config = marimow::Config::default();
+
+        let first_pass = tempfile::NamedTempFile::new().unwrap();
+        marimow::run_convert_command(&source_path, first_pass.path(), &config).unwrap();
+
+        let round_tripped = tempfile::NamedTempFile::new().unwrap();
+        marimow::run_unconvert_command(first_pass.path(), round_tripped.path(), &config).unwrap();
+
+        let second_pass = tempfile::NamedTempFile::new().unwrap();
+        marimow::run_convert_command(round_tripped.path(), second_pass.path(), &config).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(first_pass.path()).unwrap(),
+            std::fs::read_to_string(second_pass.path()).unwrap(),
+            "conversion isn't idempotent for {fixture}",
+        );
+    }
+}